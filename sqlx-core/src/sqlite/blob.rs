@@ -0,0 +1,376 @@
+use core::ptr::NonNull;
+use core::task::{Context, Poll};
+use std::future::Future;
+use std::io;
+use std::os::raw::c_int;
+use std::pin::Pin;
+
+use futures_util::io::{AsyncRead, AsyncSeek, AsyncWrite};
+use libsqlite3_sys::{
+    sqlite3_blob, sqlite3_blob_bytes, sqlite3_blob_close, sqlite3_blob_open, sqlite3_blob_read,
+    sqlite3_blob_reopen, sqlite3_blob_write, SQLITE_OK,
+};
+
+use crate::sqlite::connection::SqliteConnectionHandle;
+use crate::sqlite::util::zero_terminate;
+use crate::sqlite::worker::Worker;
+use crate::sqlite::{Sqlite, SqliteConnection, SqliteError};
+
+/// Thin wrapper around `sqlite3_blob` to impl `Send`.
+///
+/// See the note on [`SqliteConnectionHandle`] for why this is sound despite
+/// `sqlite3_blob` not being safe for general-purpose concurrent access.
+#[derive(Clone, Copy)]
+struct SqliteBlobHandle(NonNull<sqlite3_blob>);
+
+#[allow(unsafe_code)]
+unsafe impl Send for SqliteBlobHandle {}
+
+/// A handle to an open, incremental-I/O BLOB, opened with
+/// [`SqliteConnection::blob_open`].
+///
+/// Unlike fetching a BLOB column through the normal row-decoding path, this
+/// streams the value's bytes through `sqlite3_blob_read`/`sqlite3_blob_write`
+/// without ever materializing the whole value in memory, which matters for
+/// multi-megabyte BLOBs. Modeled on rusqlite's `blob` module.
+///
+/// The BLOB has a fixed size for its lifetime; writes and seeks past that
+/// size are an error rather than growing the BLOB (use an UPDATE to change
+/// its size first, then [`SqliteBlob::reopen`] onto the new row if needed).
+/// Reads stop at the end of the BLOB: a read is cut short to the bytes that
+/// remain, and a read at the end returns 0 bytes.
+pub struct SqliteBlob {
+    handle: SqliteBlobHandle,
+    connection: SqliteConnectionHandle,
+    worker: Worker,
+
+    size: usize,
+    position: usize,
+
+    // An in-flight blocking call being driven to completion by the
+    // `AsyncRead`/`AsyncWrite` polling methods below. Resolves to the
+    // number of bytes transferred and, for reads, the bytes themselves
+    // (empty for writes).
+    pending: Option<Pin<Box<dyn Future<Output = io::Result<(usize, Vec<u8>)>> + Send>>>,
+}
+
+#[allow(unsafe_code)]
+unsafe impl Send for SqliteBlob {}
+
+impl SqliteBlob {
+    /// Opens an incremental-I/O handle onto a single BLOB value, identified
+    /// by its database, table, column, and `rowid`.
+    ///
+    /// Set `readwrite` to request write access; attempting to write through
+    /// a handle opened read-only is an error from SQLite itself.
+    pub(super) async fn open(
+        conn: &mut SqliteConnection,
+        db_name: &str,
+        table_name: &str,
+        column_name: &str,
+        rowid: i64,
+        readwrite: bool,
+    ) -> crate::Result<Sqlite, Self> {
+        let connection = conn.handle;
+        let worker = conn.worker.clone();
+
+        let db_name = zero_terminate(db_name);
+        let table_name = zero_terminate(table_name);
+        let column_name = zero_terminate(column_name);
+
+        let (status, blob) = worker
+            .run(move || {
+                let mut blob: *mut sqlite3_blob = std::ptr::null_mut();
+
+                // <https://www.sqlite.org/c3ref/blob_open.html>
+                #[allow(unsafe_code)]
+                let status = unsafe {
+                    sqlite3_blob_open(
+                        connection.0.as_ptr(),
+                        db_name.as_ptr() as *const i8,
+                        table_name.as_ptr() as *const i8,
+                        column_name.as_ptr() as *const i8,
+                        rowid,
+                        readwrite as c_int,
+                        &mut blob,
+                    )
+                };
+
+                (status, blob)
+            })
+            .await;
+
+        if status != SQLITE_OK {
+            #[allow(unsafe_code)]
+            return Err(SqliteError::from_connection(unsafe { connection.0.as_ptr() }).into());
+        }
+
+        #[allow(unsafe_code)]
+        let handle = SqliteBlobHandle(NonNull::new(blob).unwrap());
+
+        #[allow(unsafe_code)]
+        let size = unsafe { sqlite3_blob_bytes(handle.0.as_ptr()) } as usize;
+
+        Ok(Self {
+            handle,
+            connection,
+            worker,
+            size,
+            position: 0,
+            pending: None,
+        })
+    }
+
+    /// The fixed size, in bytes, of the BLOB this handle is open on.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Moves this handle to point at the same column of a different row,
+    /// without closing and reopening it.
+    ///
+    /// This is substantially cheaper than dropping and re-opening a new
+    /// [`SqliteBlob`] when streaming through many rows of the same column.
+    pub async fn reopen(&mut self, rowid: i64) -> crate::Result<Sqlite, ()> {
+        let handle = self.handle;
+        let connection = self.connection;
+
+        let status = self
+            .worker
+            .run(move || {
+                // <https://www.sqlite.org/c3ref/blob_reopen.html>
+                #[allow(unsafe_code)]
+                unsafe {
+                    sqlite3_blob_reopen(handle.0.as_ptr(), rowid)
+                }
+            })
+            .await;
+
+        if status != SQLITE_OK {
+            #[allow(unsafe_code)]
+            return Err(SqliteError::from_connection(unsafe { connection.0.as_ptr() }).into());
+        }
+
+        #[allow(unsafe_code)]
+        let size = unsafe { sqlite3_blob_bytes(handle.0.as_ptr()) } as usize;
+
+        self.size = size;
+        self.position = 0;
+
+        Ok(())
+    }
+
+    fn read_at(
+        &self,
+        offset: usize,
+        buf_len: usize,
+    ) -> Pin<Box<dyn Future<Output = io::Result<(usize, Vec<u8>)>> + Send>> {
+        let handle = self.handle;
+        let connection = self.connection;
+        let size = self.size;
+        let worker = self.worker.clone();
+
+        Box::pin(async move {
+            let n = buf_len.min(size.saturating_sub(offset));
+
+            if n == 0 {
+                return Ok((0, Vec::new()));
+            }
+
+            let mut out = vec![0u8; n];
+            let out_ptr = out.as_mut_ptr();
+
+            let status = worker
+                .run(move || {
+                    // <https://www.sqlite.org/c3ref/blob_read.html>
+                    #[allow(unsafe_code)]
+                    unsafe {
+                        sqlite3_blob_read(
+                            handle.0.as_ptr(),
+                            out_ptr as *mut _,
+                            n as c_int,
+                            offset as c_int,
+                        )
+                    }
+                })
+                .await;
+
+            if status != SQLITE_OK {
+                #[allow(unsafe_code)]
+                let err = unsafe { SqliteError::from_connection(connection.0.as_ptr()) };
+                return Err(io::Error::new(io::ErrorKind::Other, err.to_string()));
+            }
+
+            Ok((n, out))
+        })
+    }
+}
+
+impl AsyncRead for SqliteBlob {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.pending.is_none() {
+            let position = self.position;
+            self.pending = Some(self.read_at(position, buf.len()));
+        }
+
+        let fut = self.pending.as_mut().unwrap();
+
+        match fut.as_mut().poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(result) => {
+                self.pending = None;
+
+                match result {
+                    Ok((n, data)) => {
+                        buf[..n].copy_from_slice(&data[..n]);
+                        self.position += n;
+                        Poll::Ready(Ok(n))
+                    }
+                    Err(e) => Poll::Ready(Err(e)),
+                }
+            }
+        }
+    }
+}
+
+impl AsyncWrite for SqliteBlob {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.position + buf.len() > self.size {
+            // Writes past the fixed BLOB size must error, not grow the BLOB.
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "write would exceed the BLOB's fixed size",
+            )));
+        }
+
+        if self.pending.is_none() {
+            let handle = self.handle;
+            let connection = self.connection;
+            let worker = self.worker.clone();
+            let offset = self.position;
+            let data = buf.to_vec();
+            let n = data.len();
+
+            self.pending = Some(Box::pin(async move {
+                if n == 0 {
+                    return Ok((0, Vec::new()));
+                }
+
+                let data_ptr = data.as_ptr();
+
+                let status = worker
+                    .run(move || {
+                        // <https://www.sqlite.org/c3ref/blob_write.html>
+                        #[allow(unsafe_code)]
+                        unsafe {
+                            sqlite3_blob_write(
+                                handle.0.as_ptr(),
+                                data_ptr as *const _,
+                                n as c_int,
+                                offset as c_int,
+                            )
+                        }
+                    })
+                    .await;
+
+                if status != SQLITE_OK {
+                    #[allow(unsafe_code)]
+                    let err = unsafe { SqliteError::from_connection(connection.0.as_ptr()) };
+                    return Err(io::Error::new(io::ErrorKind::Other, err.to_string()));
+                }
+
+                Ok((n, Vec::new()))
+            }));
+        }
+
+        let fut = self.pending.as_mut().unwrap();
+
+        match fut.as_mut().poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(result) => {
+                self.pending = None;
+
+                match result {
+                    Ok((n, _)) => {
+                        self.position += n;
+                        Poll::Ready(Ok(n))
+                    }
+                    Err(e) => Poll::Ready(Err(e)),
+                }
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // Every write above is already synchronously committed through
+        // `sqlite3_blob_write` on the worker thread; there is no buffering
+        // to flush.
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl AsyncSeek for SqliteBlob {
+    fn poll_seek(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        pos: io::SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        let new_position = match pos {
+            io::SeekFrom::Start(offset) => offset as i64,
+            io::SeekFrom::End(offset) => self.size as i64 + offset,
+            io::SeekFrom::Current(offset) => self.position as i64 + offset,
+        };
+
+        if new_position < 0 || new_position as usize > self.size {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek position is out of bounds for this BLOB's fixed size",
+            )));
+        }
+
+        self.position = new_position as usize;
+        Poll::Ready(Ok(self.position as u64))
+    }
+}
+
+impl SqliteConnection {
+    /// Opens a streaming, incremental-I/O handle onto a single BLOB value.
+    ///
+    /// See [`SqliteBlob`] for the read/write/seek semantics this exposes.
+    pub async fn blob_open(
+        &mut self,
+        db_name: &str,
+        table_name: &str,
+        column_name: &str,
+        rowid: i64,
+        readwrite: bool,
+    ) -> crate::Result<Sqlite, SqliteBlob> {
+        SqliteBlob::open(self, db_name, table_name, column_name, rowid, readwrite).await
+    }
+}
+
+impl Drop for SqliteBlob {
+    fn drop(&mut self) {
+        // https://sqlite.org/c3ref/blob_close.html
+        #[allow(unsafe_code)]
+        unsafe {
+            let _ = sqlite3_blob_close(self.handle.0.as_ptr());
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use libsqlite3_sys::sqlite3_finalize;
+
+use crate::sqlite::statement::SqliteStatementHandle;
+use crate::sqlite::SqliteConnection;
+
+/// The default number of prepared statements kept alive per-connection when
+/// a capacity has not been explicitly configured with
+/// [`set_cached_statement_capacity`][crate::sqlite::SqliteConnection::set_cached_statement_capacity].
+///
+/// This mirrors the default used by rusqlite's `StatementCache`.
+pub(super) const DEFAULT_STATEMENT_CACHE_CAPACITY: usize = 100;
+
+/// An LRU cache of prepared (`SQLITE_PREPARE_PERSISTENT`) statements, keyed
+/// on the trimmed SQL text that produced them.
+///
+/// This is shared (via an internal `Arc<Mutex<..>>`) between a
+/// `SqliteConnection` and every [`Statement`][crate::sqlite::statement::Statement]
+/// it prepares, so that a statement can return itself to the cache on
+/// `Drop` instead of being finalized.
+#[derive(Clone)]
+pub(super) struct StatementCache {
+    shared: Arc<Mutex<Shared>>,
+}
+
+struct Shared {
+    capacity: usize,
+
+    // `order` tracks recency with the least-recently-used key at the front
+    // and the most-recently-used key at the back.
+    order: VecDeque<Arc<str>>,
+    entries: HashMap<Arc<str>, SqliteStatementHandle>,
+}
+
+impl StatementCache {
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(Shared {
+                capacity,
+                order: VecDeque::with_capacity(capacity),
+                entries: HashMap::with_capacity(capacity),
+            })),
+        }
+    }
+
+    /// Removes and returns a cached statement prepared from `sql`, if any.
+    ///
+    /// The caller is responsible for calling `sqlite3_reset` and
+    /// `sqlite3_clear_bindings` on the returned handle before use; see
+    /// [`Statement::new`][crate::sqlite::statement::Statement::new].
+    pub(super) fn pop(&self, sql: &str) -> Option<(Arc<str>, SqliteStatementHandle)> {
+        let mut shared = self.shared.lock().unwrap();
+
+        let index = shared.order.iter().position(|key| &**key == sql)?;
+        let key = shared.order.remove(index).unwrap();
+        let handle = shared.entries.remove(&key).unwrap();
+
+        Some((key, handle))
+    }
+
+    /// Inserts a freshly-prepared statement into the cache, evicting (and
+    /// finalizing) the least-recently-used entry if this would exceed the
+    /// configured capacity.
+    ///
+    /// If the cache has zero capacity, `handle` is finalized immediately.
+    pub(super) fn put(&self, key: Arc<str>, handle: SqliteStatementHandle) {
+        let mut shared = self.shared.lock().unwrap();
+
+        if shared.capacity == 0 {
+            drop(shared);
+            finalize(handle);
+            return;
+        }
+
+        // A second `Statement` can be prepared from the same SQL text while
+        // the first is still outstanding (e.g. a recursive or nested
+        // identical query), and both will eventually return themselves to
+        // the cache under the same key. Drop the stale `order` entry for
+        // `key` up front rather than pushing a duplicate, which would
+        // desync the LRU bookkeeping from `entries`.
+        if let Some(index) = shared.order.iter().position(|k| *k == key) {
+            shared.order.remove(index);
+        }
+
+        while shared.order.len() >= shared.capacity {
+            if let Some(oldest) = shared.order.pop_front() {
+                if let Some(evicted) = shared.entries.remove(&oldest) {
+                    finalize(evicted);
+                }
+            } else {
+                break;
+            }
+        }
+
+        shared.order.push_back(key.clone());
+
+        // If `key` was already present in `entries` (the case above), this
+        // displaces its old handle rather than silently overwriting it;
+        // finalize it instead of leaking it.
+        if let Some(displaced) = shared.entries.insert(key, handle) {
+            finalize(displaced);
+        }
+    }
+
+    /// Finalizes every cached statement and empties the cache.
+    pub(super) fn clear(&self) {
+        let mut shared = self.shared.lock().unwrap();
+
+        for (_, handle) in shared.entries.drain() {
+            finalize(handle);
+        }
+
+        shared.order.clear();
+    }
+
+    pub(super) fn set_capacity(&self, capacity: usize) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.capacity = capacity;
+
+        while shared.order.len() > capacity {
+            if let Some(oldest) = shared.order.pop_front() {
+                if let Some(evicted) = shared.entries.remove(&oldest) {
+                    finalize(evicted);
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub(super) fn len(&self) -> usize {
+        self.shared.lock().unwrap().order.len()
+    }
+}
+
+impl Default for StatementCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_STATEMENT_CACHE_CAPACITY)
+    }
+}
+
+fn finalize(handle: SqliteStatementHandle) {
+    // https://sqlite.org/c3ref/finalize.html
+    #[allow(unsafe_code)]
+    unsafe {
+        let _ = sqlite3_finalize(handle.as_ptr());
+    }
+}
+
+impl SqliteConnection {
+    /// Overrides the number of persistent (`persistent: true`) statements
+    /// this connection keeps compiled and ready for reuse, evicting and
+    /// finalizing the least-recently-used entries if the new capacity is
+    /// smaller than the current contents.
+    ///
+    /// Defaults to [`DEFAULT_STATEMENT_CACHE_CAPACITY`].
+    pub fn set_cached_statement_capacity(&mut self, capacity: usize) {
+        self.statement_cache.set_capacity(capacity);
+    }
+
+    /// Finalizes every currently-cached persistent statement and empties
+    /// the cache.
+    pub fn clear_cached_statements(&mut self) {
+        self.statement_cache.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pop_misses_on_empty_cache() {
+        let cache = StatementCache::new(2);
+        assert!(cache.pop("SELECT 1").is_none());
+    }
+
+    #[test]
+    fn put_then_pop_hits_and_removes() {
+        let cache = StatementCache::new(2);
+        let handle = SqliteStatementHandle::for_test();
+        let ptr = handle.as_ptr();
+
+        cache.put(Arc::from("SELECT 1"), handle);
+        assert_eq!(cache.len(), 1);
+
+        let (key, popped) = cache.pop("SELECT 1").expect("should hit");
+        assert_eq!(&*key, "SELECT 1");
+        assert_eq!(popped.as_ptr(), ptr);
+
+        // `pop` hands ownership back to the caller rather than finalizing.
+        assert_eq!(cache.len(), 0);
+        assert!(cache.pop("SELECT 1").is_none());
+
+        finalize(popped);
+    }
+
+    #[test]
+    fn eviction_finalizes_the_least_recently_used_entry() {
+        let cache = StatementCache::new(1);
+
+        cache.put(Arc::from("SELECT 1"), SqliteStatementHandle::for_test());
+        assert_eq!(cache.len(), 1);
+
+        // Capacity is 1, so this evicts (and finalizes) "SELECT 1" rather
+        // than growing the cache.
+        cache.put(Arc::from("SELECT 2"), SqliteStatementHandle::for_test());
+        assert_eq!(cache.len(), 1);
+
+        assert!(cache.pop("SELECT 1").is_none());
+        let (_, handle) = cache.pop("SELECT 2").expect("should still be cached");
+        finalize(handle);
+    }
+
+    #[test]
+    fn put_with_duplicate_key_finalizes_the_displaced_handle() {
+        let cache = StatementCache::new(2);
+
+        // Two live `Statement`s prepared from the same SQL text (e.g. a
+        // recursive/nested identical query) both eventually call `put` with
+        // the same key; the second `put` must not silently leak the first
+        // handle or desync `order`/`entries`.
+        let first = SqliteStatementHandle::for_test();
+        let first_ptr = first.as_ptr();
+        cache.put(Arc::from("SELECT 1"), first);
+        assert_eq!(cache.len(), 1);
+
+        let second = SqliteStatementHandle::for_test();
+        let second_ptr = second.as_ptr();
+        cache.put(Arc::from("SELECT 1"), second);
+
+        // Still exactly one entry under the key, not a duplicate in `order`.
+        assert_eq!(cache.len(), 1);
+        assert_ne!(first_ptr, second_ptr);
+
+        let (_, popped) = cache.pop("SELECT 1").expect("should hit");
+        assert_eq!(popped.as_ptr(), second_ptr);
+        assert_eq!(cache.len(), 0);
+
+        finalize(popped);
+        // `first` was finalized internally by the second `put`; nothing left
+        // to finalize here.
+    }
+
+    #[test]
+    fn zero_capacity_finalizes_immediately() {
+        let cache = StatementCache::new(0);
+
+        cache.put(Arc::from("SELECT 1"), SqliteStatementHandle::for_test());
+
+        assert_eq!(cache.len(), 0);
+        assert!(cache.pop("SELECT 1").is_none());
+    }
+}
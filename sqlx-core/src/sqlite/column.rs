@@ -0,0 +1,70 @@
+/// Metadata describing a single column of a [`Statement`](super::statement::Statement)'s
+/// result set.
+///
+/// Built once, right after a statement is prepared, from
+/// `sqlite3_column_name`/`sqlite3_column_decltype` and — when the
+/// `column-metadata` Cargo feature is enabled — the
+/// `sqlite3_column_origin_name`/`_table_name`/`_database_name` family, which
+/// report where a result column actually came from in the schema.
+#[derive(Debug, Clone)]
+pub struct SqliteColumn {
+    pub(super) ordinal: usize,
+    pub(super) name: String,
+    pub(super) decltype: Option<String>,
+
+    #[cfg(feature = "column-metadata")]
+    pub(super) origin_name: Option<String>,
+    #[cfg(feature = "column-metadata")]
+    pub(super) table_name: Option<String>,
+    #[cfg(feature = "column-metadata")]
+    pub(super) database_name: Option<String>,
+}
+
+impl SqliteColumn {
+    /// The zero-based position of this column in the result set.
+    pub fn ordinal(&self) -> usize {
+        self.ordinal
+    }
+
+    /// The column's name, as it appears in the result set (possibly an
+    /// alias given by `AS`).
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The column's declared type from its originating `CREATE TABLE`, or
+    /// `None` if it is the result of an expression or subquery, or the
+    /// originating table has no declared type for it.
+    pub fn decltype(&self) -> Option<&str> {
+        self.decltype.as_deref()
+    }
+
+    /// The original, un-aliased name of the table column this result
+    /// column is a copy of, or `None` if it is the result of an expression
+    /// or subquery.
+    ///
+    /// Requires the `column-metadata` Cargo feature.
+    #[cfg(feature = "column-metadata")]
+    pub fn origin_name(&self) -> Option<&str> {
+        self.origin_name.as_deref()
+    }
+
+    /// The name of the table this result column originates from, or `None`
+    /// if it is the result of an expression or subquery.
+    ///
+    /// Requires the `column-metadata` Cargo feature.
+    #[cfg(feature = "column-metadata")]
+    pub fn table_name(&self) -> Option<&str> {
+        self.table_name.as_deref()
+    }
+
+    /// The name of the database (e.g. `main`, `temp`, or an attached
+    /// database) this result column originates from, or `None` if it is
+    /// the result of an expression or subquery.
+    ///
+    /// Requires the `column-metadata` Cargo feature.
+    #[cfg(feature = "column-metadata")]
+    pub fn database_name(&self) -> Option<&str> {
+        self.database_name.as_deref()
+    }
+}
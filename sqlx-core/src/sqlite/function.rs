@@ -0,0 +1,530 @@
+use std::ffi::{c_void, CString};
+use std::mem::MaybeUninit;
+use std::os::raw::c_int;
+use std::slice;
+
+use libsqlite3_sys::{
+    sqlite3_aggregate_context, sqlite3_context, sqlite3_create_function_v2, sqlite3_result_blob,
+    sqlite3_result_double, sqlite3_result_error, sqlite3_result_int64, sqlite3_result_null,
+    sqlite3_result_text, sqlite3_user_data, sqlite3_value, sqlite3_value_blob,
+    sqlite3_value_bytes, sqlite3_value_double, sqlite3_value_int64, sqlite3_value_text,
+    sqlite3_value_type, SQLITE_BLOB, SQLITE_DETERMINISTIC, SQLITE_DIRECTONLY, SQLITE_FLOAT,
+    SQLITE_INTEGER, SQLITE_OK, SQLITE_TEXT, SQLITE_TRANSIENT, SQLITE_UTF8,
+};
+
+use crate::sqlite::{Sqlite, SqliteConnection, SqliteError};
+
+/// Controls the `SQLITE_DETERMINISTIC`/`SQLITE_DIRECTONLY` hints passed to
+/// `sqlite3_create_function_v2`.
+///
+/// A deterministic function always returns the same result for the same
+/// arguments within a single SQL statement, which lets the query planner
+/// fold it as a constant; a direct-only function may only be invoked from
+/// top-level SQL, never from triggers or views, which matters for functions
+/// with side effects.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FunctionFlags {
+    pub deterministic: bool,
+    pub direct_only: bool,
+}
+
+impl FunctionFlags {
+    fn bits(self) -> c_int {
+        let mut flags = SQLITE_UTF8;
+
+        if self.deterministic {
+            flags |= SQLITE_DETERMINISTIC;
+        }
+
+        if self.direct_only {
+            flags |= SQLITE_DIRECTONLY;
+        }
+
+        flags
+    }
+}
+
+/// The value a user-defined function or aggregate hands back to SQLite.
+///
+/// Mirrors the small set of native SQLite types; stands in for decoding
+/// into sqlx's richer `SqliteValue`/`Encode` machinery, which this module
+/// does not otherwise touch.
+pub enum FunctionResult {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+/// The arguments passed to a single invocation of a user-defined function,
+/// along with the `sqlite3_context` needed to report errors.
+pub struct Context<'a> {
+    ctx: *mut sqlite3_context,
+    args: &'a [*mut sqlite3_value],
+}
+
+impl<'a> Context<'a> {
+    /// The number of arguments this invocation was called with.
+    pub fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+
+    fn arg(&self, index: usize) -> *mut sqlite3_value {
+        self.args[index]
+    }
+
+    /// Reads argument `index` as whichever native type SQLite stored it as.
+    pub fn get(&self, index: usize) -> FunctionResult {
+        let value = self.arg(index);
+
+        #[allow(unsafe_code)]
+        unsafe {
+            match sqlite3_value_type(value) {
+                SQLITE_INTEGER => FunctionResult::Integer(sqlite3_value_int64(value)),
+                SQLITE_FLOAT => FunctionResult::Real(sqlite3_value_double(value)),
+                SQLITE_TEXT => {
+                    let ptr = sqlite3_value_text(value);
+                    let len = sqlite3_value_bytes(value) as usize;
+                    let bytes = slice::from_raw_parts(ptr as *const u8, len);
+                    FunctionResult::Text(String::from_utf8_lossy(bytes).into_owned())
+                }
+                SQLITE_BLOB => {
+                    let ptr = sqlite3_value_blob(value);
+                    let len = sqlite3_value_bytes(value) as usize;
+                    let bytes = slice::from_raw_parts(ptr as *const u8, len);
+                    FunctionResult::Blob(bytes.to_vec())
+                }
+                // SQLITE_NULL, plus any future type we don't recognize.
+                _ => FunctionResult::Null,
+            }
+        }
+    }
+}
+
+#[allow(unsafe_code)]
+fn set_result(ctx: *mut sqlite3_context, result: FunctionResult) {
+    unsafe {
+        match result {
+            FunctionResult::Null => sqlite3_result_null(ctx),
+            FunctionResult::Integer(i) => sqlite3_result_int64(ctx, i),
+            FunctionResult::Real(d) => sqlite3_result_double(ctx, d),
+            FunctionResult::Text(s) => {
+                // Pass `SQLITE_TRANSIENT` so SQLite copies the bytes before
+                // returning, rather than handing it a destructor for a
+                // NUL-terminated `CString`: a `String` is permitted to
+                // contain embedded NUL bytes, and since we pass the byte
+                // length explicitly SQLite copies exactly that many bytes
+                // regardless, so there's no need for (and no risk of a
+                // length/allocation mismatch from) a custom destructor.
+                let bytes = s.as_bytes();
+                sqlite3_result_text(
+                    ctx,
+                    bytes.as_ptr() as *const i8,
+                    bytes.len() as c_int,
+                    SQLITE_TRANSIENT,
+                );
+            }
+            FunctionResult::Blob(b) => {
+                // Same reasoning as above: let SQLite copy the bytes
+                // immediately instead of taking ownership of our
+                // allocation through a custom destructor.
+                sqlite3_result_blob(
+                    ctx,
+                    b.as_ptr() as *const c_void,
+                    b.len() as c_int,
+                    SQLITE_TRANSIENT,
+                );
+            }
+        }
+    }
+}
+
+fn report_error(ctx: *mut sqlite3_context, message: &str) {
+    let cstring = CString::new(message).unwrap_or_default();
+
+    #[allow(unsafe_code)]
+    unsafe {
+        sqlite3_result_error(ctx, cstring.as_ptr(), -1);
+    }
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn call_scalar<F>(
+    ctx: *mut sqlite3_context,
+    argc: c_int,
+    argv: *mut *mut sqlite3_value,
+) where
+    F: Fn(&Context) -> crate::Result<Sqlite, FunctionResult> + Send + Sync + 'static,
+{
+    let f = &*(sqlite3_user_data(ctx) as *const F);
+    let args = slice::from_raw_parts(argv, argc as usize);
+    let cx = Context { ctx, args };
+
+    match f(&cx) {
+        Ok(result) => set_result(ctx, result),
+        Err(e) => report_error(ctx, &e.to_string()),
+    }
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn drop_boxed<F>(data: *mut c_void) {
+    drop(Box::from_raw(data as *mut F));
+}
+
+impl SqliteConnection {
+    /// Registers a scalar SQL function backed by a Rust closure.
+    ///
+    /// `n_args` is the number of arguments the function accepts, or `-1`
+    /// for a variadic function. Implemented via `sqlite3_create_function_v2`:
+    /// `f` is boxed and stashed as the function's user-data pointer, and the
+    /// destructor passed to SQLite drops that box when the function is
+    /// replaced or the connection closes, so it is never leaked.
+    pub fn create_function<F>(
+        &mut self,
+        name: &str,
+        n_args: i32,
+        flags: FunctionFlags,
+        f: F,
+    ) -> crate::Result<Sqlite, ()>
+    where
+        F: Fn(&Context) -> crate::Result<Sqlite, FunctionResult> + Send + Sync + 'static,
+    {
+        let name = CString::new(name).expect("function name must not contain a NUL byte");
+
+        let user_data = Box::into_raw(Box::new(f)) as *mut c_void;
+
+        #[allow(unsafe_code)]
+        let status = unsafe {
+            sqlite3_create_function_v2(
+                self.handle(),
+                name.as_ptr(),
+                n_args,
+                flags.bits(),
+                user_data,
+                Some(call_scalar::<F>),
+                None,
+                None,
+                Some(drop_boxed::<F>),
+            )
+        };
+
+        if status != SQLITE_OK {
+            // `sqlite3_create_function_v2` did not take ownership; drop it
+            // ourselves instead of leaking.
+            #[allow(unsafe_code)]
+            unsafe {
+                drop(Box::from_raw(user_data as *mut F));
+            }
+
+            return Err(SqliteError::from_connection(self.handle()).into());
+        }
+
+        Ok(())
+    }
+
+    /// Registers a user-defined aggregate function backed by an
+    /// [`Aggregate`] implementation.
+    pub fn create_aggregate<A>(
+        &mut self,
+        name: &str,
+        n_args: i32,
+        flags: FunctionFlags,
+        aggregate: A,
+    ) -> crate::Result<Sqlite, ()>
+    where
+        A: Aggregate,
+    {
+        let name = CString::new(name).expect("function name must not contain a NUL byte");
+
+        let user_data = Box::into_raw(Box::new(aggregate)) as *mut c_void;
+
+        #[allow(unsafe_code)]
+        let status = unsafe {
+            sqlite3_create_function_v2(
+                self.handle(),
+                name.as_ptr(),
+                n_args,
+                flags.bits(),
+                user_data,
+                None,
+                Some(call_aggregate_step::<A>),
+                Some(call_aggregate_final::<A>),
+                Some(drop_boxed::<A>),
+            )
+        };
+
+        if status != SQLITE_OK {
+            #[allow(unsafe_code)]
+            unsafe {
+                drop(Box::from_raw(user_data as *mut A));
+            }
+
+            return Err(SqliteError::from_connection(self.handle()).into());
+        }
+
+        Ok(())
+    }
+}
+
+/// The per-aggregate-group accumulator for a [`SqliteConnection::create_aggregate`]
+/// registration, storing state through `sqlite3_aggregate_context` the same
+/// way rusqlite's `Aggregate` trait does.
+pub trait Aggregate: Send + Sync + 'static {
+    /// The running state accumulated across calls to [`Self::step`].
+    type State: Default + Send;
+
+    /// Called once per row in the group.
+    fn step(&self, state: &mut Self::State, ctx: &Context) -> crate::Result<Sqlite, ()>;
+
+    /// Called once after the last row in the group to produce the result.
+    fn finalize(
+        &self,
+        state: Option<Self::State>,
+        ctx: &Context,
+    ) -> crate::Result<Sqlite, FunctionResult>;
+}
+
+// `sqlite3_aggregate_context` hands back a zeroed block of memory the first
+// time it's called for a given aggregate invocation, and the same pointer
+// on every subsequent call within that group; `initialized` tracks whether
+// `state` has actually been constructed yet, since zeroed memory is not a
+// valid `A::State` in general.
+struct AggregateSlot<S> {
+    initialized: bool,
+    state: MaybeUninit<S>,
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn call_aggregate_step<A: Aggregate>(
+    ctx: *mut sqlite3_context,
+    argc: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    let aggregate = &*(sqlite3_user_data(ctx) as *const A);
+
+    let slot = sqlite3_aggregate_context(ctx, std::mem::size_of::<AggregateSlot<A::State>>() as c_int)
+        as *mut AggregateSlot<A::State>;
+
+    if slot.is_null() {
+        // SQLite has already recorded an OOM error for this invocation.
+        return;
+    }
+
+    if !(*slot).initialized {
+        (*slot).state = MaybeUninit::new(A::State::default());
+        (*slot).initialized = true;
+    }
+
+    let args = slice::from_raw_parts(argv, argc as usize);
+    let cx = Context { ctx, args };
+
+    if let Err(e) = aggregate.step(&mut *(*slot).state.as_mut_ptr(), &cx) {
+        report_error(ctx, &e.to_string());
+    }
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn call_aggregate_final<A: Aggregate>(ctx: *mut sqlite3_context) {
+    let aggregate = &*(sqlite3_user_data(ctx) as *const A);
+
+    // Passing 0 here asks SQLite not to allocate if no `step` was ever
+    // called for this group (e.g. `SELECT my_agg() FROM empty_table`); in
+    // that case we pass `None` through to `finalize`.
+    let slot = sqlite3_aggregate_context(ctx, 0) as *mut AggregateSlot<A::State>;
+
+    let state = if slot.is_null() || !(*slot).initialized {
+        None
+    } else {
+        Some((*slot).state.as_ptr().read())
+    };
+
+    let cx = Context { ctx, args: &[] };
+
+    match aggregate.finalize(state, &cx) {
+        Ok(result) => set_result(ctx, result),
+        Err(e) => report_error(ctx, &e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::ptr;
+
+    use libsqlite3_sys::{
+        sqlite3, sqlite3_column_bytes, sqlite3_column_int64, sqlite3_column_text,
+        sqlite3_finalize, sqlite3_open_v2, sqlite3_prepare_v2, sqlite3_step, sqlite3_stmt,
+        SQLITE_OPEN_CREATE, SQLITE_OPEN_READWRITE, SQLITE_ROW,
+    };
+
+    use super::*;
+
+    // These tests register functions directly against a raw `sqlite3*`
+    // via the same trampolines `SqliteConnection::create_function`/
+    // `create_aggregate` use, rather than going through `SqliteConnection`
+    // itself, since that type's connection-opening/worker machinery lives
+    // outside this module.
+
+    #[allow(unsafe_code)]
+    fn open_in_memory() -> *mut sqlite3 {
+        let mut db: *mut sqlite3 = ptr::null_mut();
+        let path = CString::new(":memory:").unwrap();
+
+        unsafe {
+            let status = sqlite3_open_v2(
+                path.as_ptr(),
+                &mut db,
+                SQLITE_OPEN_READWRITE | SQLITE_OPEN_CREATE,
+                ptr::null(),
+            );
+            assert_eq!(status, SQLITE_OK);
+        }
+
+        db
+    }
+
+    #[allow(unsafe_code)]
+    fn register_scalar<F>(db: *mut sqlite3, name: &str, n_args: i32, f: F)
+    where
+        F: Fn(&Context) -> crate::Result<Sqlite, FunctionResult> + Send + Sync + 'static,
+    {
+        let name = CString::new(name).unwrap();
+        let user_data = Box::into_raw(Box::new(f)) as *mut c_void;
+
+        unsafe {
+            let status = sqlite3_create_function_v2(
+                db,
+                name.as_ptr(),
+                n_args,
+                SQLITE_UTF8,
+                user_data,
+                Some(call_scalar::<F>),
+                None,
+                None,
+                Some(drop_boxed::<F>),
+            );
+            assert_eq!(status, SQLITE_OK);
+        }
+    }
+
+    #[allow(unsafe_code)]
+    fn register_aggregate<A: Aggregate>(db: *mut sqlite3, name: &str, n_args: i32, aggregate: A) {
+        let name = CString::new(name).unwrap();
+        let user_data = Box::into_raw(Box::new(aggregate)) as *mut c_void;
+
+        unsafe {
+            let status = sqlite3_create_function_v2(
+                db,
+                name.as_ptr(),
+                n_args,
+                SQLITE_UTF8,
+                user_data,
+                None,
+                Some(call_aggregate_step::<A>),
+                Some(call_aggregate_final::<A>),
+                Some(drop_boxed::<A>),
+            );
+            assert_eq!(status, SQLITE_OK);
+        }
+    }
+
+    #[allow(unsafe_code)]
+    fn eval_text(db: *mut sqlite3, sql: &str) -> Vec<u8> {
+        unsafe {
+            let sql = CString::new(sql).unwrap();
+            let mut stmt: *mut sqlite3_stmt = ptr::null_mut();
+            let status = sqlite3_prepare_v2(db, sql.as_ptr(), -1, &mut stmt, ptr::null_mut());
+            assert_eq!(status, SQLITE_OK);
+            assert_eq!(sqlite3_step(stmt), SQLITE_ROW);
+
+            let ptr = sqlite3_column_text(stmt, 0);
+            let len = sqlite3_column_bytes(stmt, 0) as usize;
+            let bytes = slice::from_raw_parts(ptr as *const u8, len).to_vec();
+
+            sqlite3_finalize(stmt);
+            bytes
+        }
+    }
+
+    #[allow(unsafe_code)]
+    fn eval_int(db: *mut sqlite3, sql: &str) -> i64 {
+        unsafe {
+            let sql = CString::new(sql).unwrap();
+            let mut stmt: *mut sqlite3_stmt = ptr::null_mut();
+            let status = sqlite3_prepare_v2(db, sql.as_ptr(), -1, &mut stmt, ptr::null_mut());
+            assert_eq!(status, SQLITE_OK);
+            assert_eq!(sqlite3_step(stmt), SQLITE_ROW);
+
+            let value = sqlite3_column_int64(stmt, 0);
+            sqlite3_finalize(stmt);
+            value
+        }
+    }
+
+    #[test]
+    fn scalar_round_trips_text_containing_an_embedded_nul() {
+        let db = open_in_memory();
+        register_scalar(db, "echo", 1, |cx: &Context| Ok(cx.get(0)));
+
+        // `char(97, 0, 98, 99)` is SQLite's builtin for building a TEXT
+        // value from code points, giving us "a\0bc" as a genuine 4-byte
+        // TEXT argument without needing a NUL literal in the SQL text
+        // itself. This is the exact shape of value that used to cause an
+        // out-of-bounds read: `CString::new` fails on the embedded NUL and
+        // the old code silently substituted an empty string while still
+        // passing the original (stale) length to `sqlite3_result_text`.
+        let got = eval_text(db, "SELECT echo(char(97, 0, 98, 99))");
+        assert_eq!(got, vec![97, 0, 98, 99]);
+    }
+
+    #[test]
+    fn scalar_round_trips_blob() {
+        let db = open_in_memory();
+        register_scalar(db, "echo", 1, |cx: &Context| Ok(cx.get(0)));
+
+        let got = eval_text(db, "SELECT echo(x'0001020304')");
+        assert_eq!(got, vec![0, 1, 2, 3, 4]);
+    }
+
+    struct CountAggregate;
+
+    impl Aggregate for CountAggregate {
+        type State = i64;
+
+        fn step(&self, state: &mut Self::State, _ctx: &Context) -> crate::Result<Sqlite, ()> {
+            *state += 1;
+            Ok(())
+        }
+
+        fn finalize(
+            &self,
+            state: Option<Self::State>,
+            _ctx: &Context,
+        ) -> crate::Result<Sqlite, FunctionResult> {
+            Ok(FunctionResult::Integer(state.unwrap_or(0)))
+        }
+    }
+
+    #[test]
+    fn aggregate_round_trips_over_several_rows() {
+        let db = open_in_memory();
+        register_aggregate(db, "my_count", 0, CountAggregate);
+
+        let got = eval_int(db, "SELECT my_count() FROM (VALUES (1), (2), (3))");
+        assert_eq!(got, 3);
+    }
+
+    #[test]
+    fn aggregate_finalize_with_no_rows_sees_no_state() {
+        let db = open_in_memory();
+        register_aggregate(db, "my_count", 0, CountAggregate);
+
+        let got = eval_int(db, "SELECT my_count() FROM (SELECT 1 WHERE 0)");
+        assert_eq!(got, 0);
+    }
+}
@@ -3,14 +3,23 @@ use core::ptr::{null, null_mut, NonNull};
 use std::collections::HashMap;
 use std::ffi::CStr;
 use std::os::raw::c_int;
+use std::sync::Arc;
 
 use libsqlite3_sys::{
     sqlite3_bind_parameter_count, sqlite3_clear_bindings, sqlite3_column_count,
     sqlite3_column_decltype, sqlite3_column_name, sqlite3_data_count, sqlite3_finalize,
-    sqlite3_prepare_v3, sqlite3_reset, sqlite3_step, sqlite3_stmt, SQLITE_DONE, SQLITE_OK,
-    SQLITE_PREPARE_NO_VTAB, SQLITE_PREPARE_PERSISTENT, SQLITE_ROW,
+    sqlite3_prepare_v3, sqlite3_reset, sqlite3_step, sqlite3_stmt, sqlite3_stmt_readonly,
+    SQLITE_DONE, SQLITE_OK, SQLITE_PREPARE_NO_VTAB, SQLITE_PREPARE_PERSISTENT, SQLITE_ROW,
+};
+#[cfg(feature = "unlock-notify")]
+use libsqlite3_sys::SQLITE_LOCKED_SHAREDCACHE;
+#[cfg(feature = "column-metadata")]
+use libsqlite3_sys::{
+    sqlite3_column_database_name, sqlite3_column_origin_name, sqlite3_column_table_name,
 };
 
+use crate::sqlite::cache::StatementCache;
+use crate::sqlite::column::SqliteColumn;
 use crate::sqlite::connection::SqliteConnectionHandle;
 use crate::sqlite::worker::Worker;
 use crate::sqlite::Sqlite;
@@ -30,16 +39,73 @@ pub(super) enum Step {
 #[derive(Clone, Copy)]
 pub(super) struct SqliteStatementHandle(NonNull<sqlite3_stmt>);
 
+impl SqliteStatementHandle {
+    #[allow(unsafe_code)]
+    pub(super) fn as_ptr(&self) -> *mut sqlite3_stmt {
+        self.0.as_ptr()
+    }
+}
+
+#[cfg(test)]
+impl SqliteStatementHandle {
+    /// Prepares a trivial, real statement against a fresh in-memory
+    /// database, for tests (e.g. of [`StatementCache`]) that need a handle
+    /// `sqlite3_finalize` can actually be called on.
+    ///
+    /// The backing connection is deliberately leaked: the statement handle
+    /// borrows from it for its whole lifetime, and these tests only ever
+    /// run for the life of the test process.
+    #[allow(unsafe_code)]
+    pub(super) fn for_test() -> Self {
+        use std::ffi::CString;
+
+        use libsqlite3_sys::{
+            sqlite3, sqlite3_open_v2, sqlite3_prepare_v2, SQLITE_OPEN_CREATE,
+            SQLITE_OPEN_READWRITE,
+        };
+
+        unsafe {
+            let mut db: *mut sqlite3 = null_mut();
+            let path = CString::new(":memory:").unwrap();
+            let status = sqlite3_open_v2(
+                path.as_ptr(),
+                &mut db,
+                SQLITE_OPEN_READWRITE | SQLITE_OPEN_CREATE,
+                null(),
+            );
+            assert_eq!(status, SQLITE_OK);
+
+            let sql = CString::new("SELECT 1").unwrap();
+            let mut stmt: *mut sqlite3_stmt = null_mut();
+            let status = sqlite3_prepare_v2(db, sql.as_ptr(), -1, &mut stmt, null_mut());
+            assert_eq!(status, SQLITE_OK);
+
+            Self(NonNull::new(stmt).unwrap())
+        }
+    }
+}
+
 /// Represents a _single_ SQL statement that has been compiled into binary
 /// form and is ready to be evaluated.
 ///
-/// The statement is finalized ( `sqlite3_finalize` ) on drop.
+/// If this statement was prepared with `persistent: true`, it is returned
+/// to the connection's [`StatementCache`](super::cache::StatementCache) on
+/// `Drop` rather than being finalized, so that an identical query can reuse
+/// the already-compiled byte-code. Otherwise it is finalized
+/// ( `sqlite3_finalize` ) on drop as before.
 pub(super) struct Statement {
     handle: SqliteStatementHandle,
     pub(super) connection: SqliteConnectionHandle,
     pub(super) worker: Worker,
     pub(super) tail: usize,
     pub(super) columns: HashMap<String, usize>,
+    pub(super) column_info: Vec<SqliteColumn>,
+
+    // The cache this statement should return itself to on drop, and the key
+    // it was (or will be) stored under. `None` for non-persistent
+    // statements, which are always finalized.
+    cache: Option<StatementCache>,
+    cache_key: Option<Arc<str>>,
 }
 
 // SQLite3 statement objects are safe to send between threads, but *not* safe
@@ -55,6 +121,46 @@ impl Statement {
         query: &mut &str,
         persistent: bool,
     ) -> crate::Result<Sqlite, Self> {
+        let trimmed = query.trim();
+
+        // A persistent statement may already be sitting in the connection's
+        // cache from a previous, identical query; if so, skip
+        // `sqlite3_prepare_v3` entirely and just reset it for reuse.
+        //
+        // The cache is keyed on the *consumed prefix* actually compiled into
+        // each statement (see `consumed` below), not the full pre-parse
+        // `trimmed` text. A lookup here therefore only hits when `trimmed`
+        // is itself exactly one statement's text; a multi-statement `query`
+        // always misses here and falls through to the normal prepare path
+        // below, which still supports it via `tail`.
+        if persistent {
+            if let Some((key, handle)) = conn.statement_cache.pop(trimmed) {
+                #[allow(unsafe_code)]
+                unsafe {
+                    let _ = sqlite3_reset(handle.as_ptr());
+                    let _ = sqlite3_clear_bindings(handle.as_ptr());
+                }
+
+                let tail = query.len();
+                *query = "";
+
+                let mut self_ = Self {
+                    worker: conn.worker.clone(),
+                    connection: conn.handle,
+                    handle,
+                    columns: HashMap::new(),
+                    column_info: Vec::new(),
+                    tail,
+                    cache: Some(conn.statement_cache.clone()),
+                    cache_key: Some(key),
+                };
+
+                self_.read_column_info();
+
+                return Ok(self_);
+            }
+        }
+
         // TODO: Error on queries that are too large
         let query_ptr = query.as_bytes().as_ptr() as *const i8;
         let query_len = query.len() as i32;
@@ -90,6 +196,16 @@ impl Statement {
         // If pzTail is not NULL then *pzTail is made to point to the first byte
         // past the end of the first SQL statement in zSql.
         let tail = (tail as usize) - (query_ptr as usize);
+
+        // The text actually compiled into this statement is only the
+        // *prefix* of `query` up to `tail`; `trimmed` is the pre-parse text
+        // of the whole (possibly multi-statement) input. Caching under the
+        // full, pre-parse `trimmed` text here would let a later identical
+        // multi-statement `query` wrongly hit this single-statement entry
+        // and have the remaining statements silently skipped, so the key
+        // must be derived from the consumed prefix instead.
+        let consumed = query[..tail].trim();
+
         *query = &query[tail..].trim();
 
         let mut self_ = Self {
@@ -97,19 +213,60 @@ impl Statement {
             connection: conn.handle,
             handle: SqliteStatementHandle(NonNull::new(statement_handle).unwrap()),
             columns: HashMap::new(),
+            column_info: Vec::new(),
             tail,
+            cache: if persistent {
+                Some(conn.statement_cache.clone())
+            } else {
+                None
+            },
+            cache_key: if persistent {
+                Some(Arc::from(consumed))
+            } else {
+                None
+            },
         };
 
-        // Prepare a column hash map for use in pulling values from a column by name
-        let count = self_.column_count();
-        self_.columns.reserve(count);
+        self_.read_column_info();
+
+        Ok(self_)
+    }
+
+    /// Populates `columns` (for lookup by name) and `column_info` (the
+    /// public per-column metadata) from the just-compiled statement.
+    ///
+    /// Shared by both branches of `new` above, since a cache hit and a
+    /// fresh `sqlite3_prepare_v3` both need to do this once the handle is
+    /// ready.
+    fn read_column_info(&mut self) {
+        let count = self.column_count();
+        self.columns.reserve(count);
+        self.column_info.reserve(count);
 
         for i in 0..count {
-            let name = self_.column_name(i).to_owned();
-            self_.columns.insert(name, i);
+            let name = self.column_name(i).to_owned();
+            self.columns.insert(name.clone(), i);
+
+            self.column_info.push(SqliteColumn {
+                ordinal: i,
+                name,
+                decltype: self.column_decltype(i).map(ToOwned::to_owned),
+
+                #[cfg(feature = "column-metadata")]
+                origin_name: self.column_origin_name(i).map(ToOwned::to_owned),
+                #[cfg(feature = "column-metadata")]
+                table_name: self.column_table_name(i).map(ToOwned::to_owned),
+                #[cfg(feature = "column-metadata")]
+                database_name: self.column_database_name(i).map(ToOwned::to_owned),
+            });
         }
+    }
 
-        Ok(self_)
+    /// Returns metadata for every column of this statement's result set,
+    /// including origin table/database (when the `column-metadata`
+    /// feature is enabled), as built by `read_column_info` above.
+    pub(crate) fn columns(&self) -> &[SqliteColumn] {
+        &self.column_info
     }
 
     /// Returns a pointer to the raw C pointer backing this statement.
@@ -169,6 +326,78 @@ impl Statement {
         name.map(|s| s.to_str().unwrap())
     }
 
+    /// Returns the original, un-aliased name of the table column that column
+    /// `index` of the result set is a copy of, or `None` if it is the
+    /// result of an expression or subquery.
+    ///
+    /// Requires `libsqlite3` to have been built with
+    /// `SQLITE_ENABLE_COLUMN_METADATA`, so this is gated behind the
+    /// `column-metadata` Cargo feature.
+    #[cfg(feature = "column-metadata")]
+    pub(super) fn column_origin_name(&mut self, index: usize) -> Option<&str> {
+        // https://sqlite.org/c3ref/column_database_name.html
+        #[allow(unsafe_code)]
+        let name = unsafe {
+            let ptr = sqlite3_column_origin_name(self.handle(), index as c_int);
+
+            if ptr.is_null() {
+                None
+            } else {
+                Some(CStr::from_ptr(ptr))
+            }
+        };
+
+        name.map(|s| s.to_str().unwrap())
+    }
+
+    /// Returns the name of the table that column `index` of the result set
+    /// originates from, or `None` if it is the result of an expression or
+    /// subquery.
+    ///
+    /// Requires `libsqlite3` to have been built with
+    /// `SQLITE_ENABLE_COLUMN_METADATA`, so this is gated behind the
+    /// `column-metadata` Cargo feature.
+    #[cfg(feature = "column-metadata")]
+    pub(super) fn column_table_name(&mut self, index: usize) -> Option<&str> {
+        // https://sqlite.org/c3ref/column_database_name.html
+        #[allow(unsafe_code)]
+        let name = unsafe {
+            let ptr = sqlite3_column_table_name(self.handle(), index as c_int);
+
+            if ptr.is_null() {
+                None
+            } else {
+                Some(CStr::from_ptr(ptr))
+            }
+        };
+
+        name.map(|s| s.to_str().unwrap())
+    }
+
+    /// Returns the name of the database (e.g. `main`, `temp`, or an attached
+    /// database) that column `index` of the result set originates from, or
+    /// `None` if it is the result of an expression or subquery.
+    ///
+    /// Requires `libsqlite3` to have been built with
+    /// `SQLITE_ENABLE_COLUMN_METADATA`, so this is gated behind the
+    /// `column-metadata` Cargo feature.
+    #[cfg(feature = "column-metadata")]
+    pub(super) fn column_database_name(&mut self, index: usize) -> Option<&str> {
+        // https://sqlite.org/c3ref/column_database_name.html
+        #[allow(unsafe_code)]
+        let name = unsafe {
+            let ptr = sqlite3_column_database_name(self.handle(), index as c_int);
+
+            if ptr.is_null() {
+                None
+            } else {
+                Some(CStr::from_ptr(ptr))
+            }
+        };
+
+        name.map(|s| s.to_str().unwrap())
+    }
+
     pub(super) fn params(&mut self) -> usize {
         // https://www.hwaci.com/sw/sqlite/c3ref/bind_parameter_count.html
         #[allow(unsafe_code)]
@@ -176,6 +405,25 @@ impl Statement {
         num as usize
     }
 
+    /// Returns `true` if this statement makes no direct changes to the
+    /// content of the database file.
+    ///
+    /// This is the primitive a read/write-splitting connection needs to
+    /// route a freshly compiled statement: read-only statements could be
+    /// dispatched onto a separate read-only connection or WAL read
+    /// snapshot, while statements that may write would need to be
+    /// serialized against the primary connection. That routing is not
+    /// implemented here; there is no executor in this crate to wire it
+    /// into yet. This is, however, reachable on the returned statement
+    /// handle (like [`Statement::columns`]), so a future executor can make
+    /// that decision without needing a visibility change here.
+    pub(crate) fn readonly(&mut self) -> bool {
+        // https://sqlite.org/c3ref/stmt_readonly.html
+        #[allow(unsafe_code)]
+        let readonly = unsafe { sqlite3_stmt_readonly(self.handle()) };
+        readonly != 0
+    }
+
     pub(super) fn bind(&mut self, arguments: &mut SqliteArguments) -> crate::Result<Sqlite, ()> {
         for index in 0..self.params() {
             if let Some(value) = arguments.next() {
@@ -207,27 +455,129 @@ impl Statement {
 
         let handle = self.handle;
 
+        loop {
+            #[allow(unsafe_code)]
+            let status = unsafe {
+                self.worker
+                    .run(move || sqlite3_step(handle.0.as_ptr()))
+                    .await
+            };
+
+            match status {
+                SQLITE_DONE => return Ok(Step::Done),
+
+                SQLITE_ROW => return Ok(Step::Row),
+
+                #[cfg(feature = "unlock-notify")]
+                SQLITE_LOCKED_SHAREDCACHE => {
+                    // Another connection sharing our cache holds a lock we
+                    // need. Block (off the async executor, on the worker
+                    // thread) until SQLite tells us it's been released via
+                    // `sqlite3_unlock_notify`, then reset and retry the step.
+                    let connection = self.connection;
+
+                    #[allow(unsafe_code)]
+                    let unlocked = self
+                        .worker
+                        .run(move || unlock_notify::wait(connection.0.as_ptr()))
+                        .await;
+
+                    if unlocked.is_err() {
+                        // A deadlock was detected; surface it instead of
+                        // blocking forever.
+                        return Err(SqliteError::from_connection(connection.0.as_ptr()).into());
+                    }
+
+                    #[allow(unsafe_code)]
+                    let _ = unsafe { sqlite3_reset(handle.0.as_ptr()) };
+                }
+
+                _ => {
+                    return Err(SqliteError::from_connection(self.connection.0.as_ptr()).into());
+                }
+            }
+        }
+    }
+}
+
+/// Implements the `sqlite3_unlock_notify` protocol used to block on
+/// `SQLITE_LOCKED_SHAREDCACHE` until the blocking connection releases its
+/// lock, instead of surfacing it as an immediate error.
+///
+/// This requires `libsqlite3` to have been built with
+/// `SQLITE_ENABLE_UNLOCK_NOTIFY`, so it is gated behind the `unlock-notify`
+/// Cargo feature.
+#[cfg(feature = "unlock-notify")]
+mod unlock_notify {
+    use std::os::raw::{c_int, c_void};
+    use std::sync::{Condvar, Mutex};
+
+    use libsqlite3_sys::{sqlite3, sqlite3_unlock_notify, SQLITE_LOCKED, SQLITE_OK};
+
+    /// Shared between the waiting thread and the `sqlite3_unlock_notify`
+    /// callback, which may run on an arbitrary SQLite-internal thread.
+    struct Notify {
+        fired: Mutex<bool>,
+        condvar: Condvar,
+    }
+
+    /// Blocks the current (worker) thread until `db`'s lock is released.
+    ///
+    /// Returns `Err(())` if SQLite reports `SQLITE_LOCKED`, which indicates
+    /// a deadlock rather than a lock we can simply wait out.
+    pub(super) fn wait(db: *mut sqlite3) -> Result<(), ()> {
+        let notify = Notify {
+            fired: Mutex::new(false),
+            condvar: Condvar::new(),
+        };
+
+        // <https://www.sqlite.org/c3ref/unlock_notify.html>
         #[allow(unsafe_code)]
         let status = unsafe {
-            self.worker
-                .run(move || sqlite3_step(handle.0.as_ptr()))
-                .await
+            sqlite3_unlock_notify(
+                db,
+                Some(callback),
+                &notify as *const Notify as *mut c_void,
+            )
         };
 
-        match status {
-            SQLITE_DONE => Ok(Step::Done),
+        if status == SQLITE_LOCKED {
+            // A deadlock was detected; the caller must surface this as an
+            // error rather than waiting forever.
+            return Err(());
+        }
 
-            SQLITE_ROW => Ok(Step::Row),
+        debug_assert_eq!(status, SQLITE_OK);
 
-            _ => {
-                return Err(SqliteError::from_connection(self.connection.0.as_ptr()).into());
-            }
+        let mut fired = notify.fired.lock().unwrap();
+        while !*fired {
+            fired = notify.condvar.wait(fired).unwrap();
+        }
+
+        Ok(())
+    }
+
+    #[allow(unsafe_code)]
+    unsafe extern "C" fn callback(ap_arg: *mut *mut c_void, n_arg: c_int) {
+        for i in 0..n_arg as isize {
+            let notify = &*(*ap_arg.offset(i) as *const Notify);
+            let mut fired = notify.fired.lock().unwrap();
+            *fired = true;
+            notify.condvar.notify_one();
         }
     }
 }
 
 impl Drop for Statement {
     fn drop(&mut self) {
+        if let (Some(cache), Some(key)) = (self.cache.take(), self.cache_key.take()) {
+            // Hand the still-compiled statement back to the connection's
+            // cache instead of finalizing it, so an identical query can
+            // reuse it without recompiling.
+            cache.put(key, self.handle);
+            return;
+        }
+
         // https://sqlite.org/c3ref/finalize.html
         #[allow(unsafe_code)]
         unsafe {
@@ -0,0 +1,9 @@
+/// NUL-terminates `s` into a fresh byte buffer, for passing to `libsqlite3-sys`
+/// functions that take a C string (e.g. `sqlite3_blob_open`, `sqlite3_backup_init`)
+/// rather than a pointer + length pair.
+pub(super) fn zero_terminate(s: &str) -> Vec<u8> {
+    let mut v = Vec::with_capacity(s.len() + 1);
+    v.extend_from_slice(s.as_bytes());
+    v.push(0);
+    v
+}